@@ -4,109 +4,37 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use rusqlite::{params, Connection};
+use rayon::prelude::*;
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Serialize;
 
+use crate::github::{FixCommit, FixPullRequest};
 use crate::osv;
+use crate::refs;
+use crate::versions;
 
-/// Extract git commit URLs
-/// Looks for patterns like:
-/// - /commits/[hash]
-/// - /commit/[hash]  
-/// - github.com/.../commit/[hash]
-/// Note: Excludes URLs that contain "/pull/" to avoid confusion with PR URLs
-fn extract_git_commits(url: &str) -> Option<Vec<String>> {
-    let mut commits = Vec::new();
-    
-    // Skip URLs that contain "/pull/" as they are pull request URLs
-    if url.contains("/pull/") {
-        return None;
-    }
-    
-    // Pattern for commit URLs: /commit/hash or /commits/hash
-    if let Some(commit_start) = url.find("/commit") {
-        let after_commit = &url[commit_start..];
-        // Handle both /commit/ and /commits/ patterns
-        let hash_start = if after_commit.starts_with("/commits/") {
-            9  // Skip "/commits/"
-        } else if after_commit.starts_with("/commit/") {
-            8  // Skip "/commit/"
-        } else {
-            return None;
-        };
-        
-        let hash_part = &after_commit[hash_start..];
-        // Extract the hash (typically 40 chars for full SHA, but could be shorter)
-        // Look for the next non-hex character or end of string
-        let mut end = 0;
-        for (i, c) in hash_part.char_indices() {
-            if c.is_ascii_hexdigit() {
-                end = i + 1;
-            } else {
-                break;
-            }
-        }
-        if end >= 7 && end <= 40 {  // Valid git hash length range
-            // Return the full URL instead of just the hash
-            commits.push(url.to_string());
-        }
-    }
-    
-    if commits.is_empty() {
-        None
-    } else {
-        Some(commits)
-    }
+/// A row from `advisories`, shaped for JSON responses rather than SQL binds.
+#[derive(Debug, Serialize)]
+pub struct AdvisorySummary {
+    pub ghsa: String,
+    pub cve: Option<String>,
+    pub ecosystems: Option<String>,
+    pub summary: Option<String>,
+    pub details: Option<String>,
+    pub severity: Option<String>,
 }
 
-/// Extract pull request URLs
-/// Looks for patterns like:
-/// - /pull/[number]
-/// - /pulls/[number]
-/// - github.com/.../pull/[number]
-fn extract_pull_requests(url: &str) -> Option<Vec<String>> {
-    let mut pull_requests = Vec::new();
-    
-    // Pattern for pull request URLs: /pull/number or /pulls/number
-    if let Some(pull_start) = url.find("/pull") {
-        let after_pull = &url[pull_start..];
-        // Handle both /pull/ and /pulls/ patterns
-        let number_start = if after_pull.starts_with("/pulls/") {
-            7  // Skip "/pulls/"
-        } else if after_pull.starts_with("/pull/") {
-            6  // Skip "/pull/"
-        } else {
-            return None;
-        };
-        
-        let number_part = &after_pull[number_start..];
-        // Extract the PR number (digits only)
-        let mut end = 0;
-        for (i, c) in number_part.char_indices() {
-            if c.is_ascii_digit() {
-                end = i + 1;
-            } else {
-                break;
-            }
-        }
-        if end > 0 {  // Valid PR number
-            // For pull request URLs, we want to extract just the base PR URL
-            // without any additional paths like /commits/hash
-            let base_url = if let Some(commits_pos) = url.find("/commits/") {
-                &url[..commits_pos]
-            } else if let Some(files_pos) = url.find("/files") {
-                &url[..files_pos]
-            } else {
-                url
-            };
-            pull_requests.push(base_url.to_string());
-        }
-    }
-    
-    if pull_requests.is_empty() {
-        None
-    } else {
-        Some(pull_requests)
-    }
+const SELECT_ADVISORY_COLUMNS: &str = "ghsa, cve, ecosystems, summary, details, severity";
+
+fn advisory_summary_from_row(row: &rusqlite::Row) -> rusqlite::Result<AdvisorySummary> {
+    Ok(AdvisorySummary {
+        ghsa: row.get(0)?,
+        cve: row.get(1)?,
+        ecosystems: row.get(2)?,
+        summary: row.get(3)?,
+        details: row.get(4)?,
+        severity: row.get(5)?,
+    })
 }
 
 pub struct DB {
@@ -130,7 +58,8 @@ CREATE TABLE advisories (
     github_reviewed_at TEXT,
     nvd_published_at TEXT,
     ref_commits TEXT,
-    ref_pull_requests TEXT
+    ref_pull_requests TEXT,
+    ref_issues TEXT
 )"#;
 
 const CREATE_AFFECTED_PACKAGES_TABLE: &str = r#"
@@ -145,10 +74,10 @@ CREATE TABLE affected_packages (
 const INSERT_ADVISORY: &str = r#"
 INSERT INTO advisories (
      ghsa,  schema_version,  modified,  published,  withdrawn,  cve,  ecosystems,  summary,
-     details,  severity,  cwes,  github_reviewed,  github_reviewed_at,  nvd_published_at,  ref_commits,  ref_pull_requests
+     details,  severity,  cwes,  github_reviewed,  github_reviewed_at,  nvd_published_at,  ref_commits,  ref_pull_requests,  ref_issues
 ) VALUES (
     :ghsa, :schema_version, :modified, :published, :withdrawn, :cve, :ecosystems, :summary,
-    :details, :severity, :cwes, :github_reviewed, :github_reviewed_at, :nvd_published_at, :ref_commits, :ref_pull_requests
+    :details, :severity, :cwes, :github_reviewed, :github_reviewed_at, :nvd_published_at, :ref_commits, :ref_pull_requests, :ref_issues
 )"#;
 
 const INSERT_AFFECTED_PACKAGE: &str = r#"
@@ -158,71 +87,246 @@ INSERT INTO affected_packages (
     :ghsa, :name, :ecosystem, :ranges, :versions
 )"#;
 
+const CREATE_FIX_COMMITS_TABLE: &str = r#"
+CREATE TABLE fix_commits (
+    ghsa TEXT NOT NULL,
+    url TEXT NOT NULL,
+    sha TEXT NOT NULL,
+    author TEXT,
+    author_date TEXT,
+    files_changed TEXT,
+    PRIMARY KEY (ghsa, url)
+)"#;
+
+const CREATE_FIX_PULL_REQUESTS_TABLE: &str = r#"
+CREATE TABLE fix_pull_requests (
+    ghsa TEXT NOT NULL,
+    url TEXT NOT NULL,
+    number INTEGER NOT NULL,
+    merged INTEGER NOT NULL,
+    merge_commit_sha TEXT,
+    merged_at TEXT,
+    branches TEXT,
+    releases TEXT,
+    PRIMARY KEY (ghsa, url)
+)"#;
+
+const INSERT_FIX_COMMIT: &str = r#"
+INSERT OR REPLACE INTO fix_commits (
+     ghsa,  url,  sha,  author,  author_date,  files_changed
+) VALUES (
+    :ghsa, :url, :sha, :author, :author_date, :files_changed
+)"#;
+
+const INSERT_FIX_PULL_REQUEST: &str = r#"
+INSERT OR REPLACE INTO fix_pull_requests (
+     ghsa,  url,  number,  merged,  merge_commit_sha,  merged_at,  branches,  releases
+) VALUES (
+    :ghsa, :url, :number, :merged, :merge_commit_sha, :merged_at, :branches, :releases
+)"#;
+
+/// The CPU-bound part of preparing one advisory for insertion: ecosystem
+/// collection, CVE alias scanning, reference classification, and
+/// serializing the bits that go into TEXT columns as JSON. Computed outside
+/// the connection `Mutex` so `bulk_insert` can run it across a thread pool.
+struct PreparedAdvisory {
+    cve: Option<String>,
+    ecosystems: Option<String>,
+    cwes: Option<serde_json::Value>,
+    nvd_published_at: Option<String>,
+    ref_commits: Option<String>,
+    ref_pull_requests: Option<String>,
+    ref_issues: Option<String>,
+    affected_packages: Vec<PreparedAffectedPackage>,
+}
+
+struct PreparedAffectedPackage {
+    ecosystem: String,
+    ranges: String,
+    versions: String,
+}
+
+fn prepare_advisory(
+    entry: &osv::GitHubAdvisory,
+) -> Result<PreparedAdvisory, Box<dyn Error + Send + Sync>> {
+    // Collect ecosystems from affected packages
+    let mut ecosystems = HashSet::new();
+    if let Some(affected) = entry.affected.as_ref() {
+        for a in affected {
+            ecosystems.insert(&a.package.ecosystem);
+        }
+    }
+    let ecosystems = if ecosystems.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&ecosystems)?)
+    };
+
+    // Extract CVE from aliases (filter for aliases starting with "CVE-")
+    let cve = entry
+        .aliases
+        .as_ref()
+        .and_then(|aliases| aliases.iter().find(|alias| alias.starts_with("CVE-")))
+        .map(|s| s.to_string());
+
+    // Classify references into commits, pull/merge requests, and issues
+    let parsed_refs = refs::parse_references(
+        entry
+            .references
+            .as_ref()
+            .into_iter()
+            .flatten()
+            .map(|reference| reference.url.as_str()),
+    );
+    let ref_commits = if parsed_refs.commits.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&parsed_refs.commits)?)
+    };
+    let ref_pull_requests = if parsed_refs.pull_requests.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&parsed_refs.pull_requests)?)
+    };
+    let ref_issues = if parsed_refs.issues.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&parsed_refs.issues)?)
+    };
+
+    let cwes = entry
+        .database_specific
+        .as_ref()
+        .and_then(|d| d.cwe_ids.as_ref())
+        .map(serde_json::to_value)
+        .transpose()?;
+    let nvd_published_at = entry
+        .database_specific
+        .as_ref()
+        .and_then(|d| d.nvd_published_at.as_ref())
+        .map(serde_json::to_string)
+        .transpose()?;
+
+    let affected_packages = entry
+        .affected
+        .as_ref()
+        .map(|affected| {
+            affected
+                .iter()
+                .map(|a| {
+                    Ok::<_, Box<dyn Error + Send + Sync>>(PreparedAffectedPackage {
+                        ecosystem: serde_json::to_value(&a.package.ecosystem)?
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        ranges: serde_json::to_string(&a.ranges)?,
+                        versions: serde_json::to_string(&a.versions)?,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    Ok(PreparedAdvisory {
+        cve,
+        ecosystems,
+        cwes,
+        nvd_published_at,
+        ref_commits,
+        ref_pull_requests,
+        ref_issues,
+        affected_packages,
+    })
+}
+
 impl DB {
     pub fn new(db_path: &str) -> Result<Self, Box<dyn Error>> {
         let conn = Connection::open(db_path)?;
         conn.execute(CREATE_ADVISORIES_TABLE, ())?;
         conn.execute(CREATE_AFFECTED_PACKAGES_TABLE, ())?;
+        conn.execute(CREATE_FIX_COMMITS_TABLE, ())?;
+        conn.execute(CREATE_FIX_PULL_REQUESTS_TABLE, ())?;
+        Ok(Self {
+            locked_conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Opens an existing database read-only, without creating the schema.
+    /// Used by [`crate::serve`] so the HTTP service can never write to a
+    /// database another process is actively ingesting into.
+    pub fn open_read_only(db_path: &str) -> Result<Self, Box<dyn Error>> {
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
         Ok(Self {
             locked_conn: Arc::new(Mutex::new(conn)),
         })
     }
 
+    pub fn get_by_ghsa(
+        &self,
+        ghsa: &str,
+    ) -> Result<Option<AdvisorySummary>, Box<dyn Error + Send + Sync>> {
+        let conn = self
+            .locked_conn
+            .lock()
+            .map_err(|e| format!("obtaining connection lock: {}", e))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SELECT_ADVISORY_COLUMNS} FROM advisories WHERE ghsa = ?1"
+        ))?;
+        let mut rows = stmt.query_map(params![ghsa], advisory_summary_from_row)?;
+        rows.next().transpose().map_err(Into::into)
+    }
+
+    pub fn get_by_cve(
+        &self,
+        cve: &str,
+    ) -> Result<Vec<AdvisorySummary>, Box<dyn Error + Send + Sync>> {
+        let conn = self
+            .locked_conn
+            .lock()
+            .map_err(|e| format!("obtaining connection lock: {}", e))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SELECT_ADVISORY_COLUMNS} FROM advisories WHERE cve = ?1"
+        ))?;
+        let rows = stmt.query_map(params![cve], advisory_summary_from_row)?;
+        rows.map(|r| r.map_err(Into::into)).collect()
+    }
+
+    pub fn get_by_package(
+        &self,
+        ecosystem: &str,
+        package: &str,
+    ) -> Result<Vec<AdvisorySummary>, Box<dyn Error + Send + Sync>> {
+        let conn = self
+            .locked_conn
+            .lock()
+            .map_err(|e| format!("obtaining connection lock: {}", e))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SELECT_ADVISORY_COLUMNS} FROM advisories \
+             WHERE ghsa IN (SELECT ghsa FROM affected_packages WHERE ecosystem = ?1 AND name = ?2)"
+        ))?;
+        let rows = stmt.query_map(params![ecosystem, package], advisory_summary_from_row)?;
+        rows.map(|r| r.map_err(Into::into)).collect()
+    }
+
     pub fn bulk_insert(
         &self,
         entries: &[osv::GitHubAdvisory],
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        // Parallel map phase: serialize everything bind-ready across a
+        // thread pool before we ever touch the connection lock.
+        let prepared: Vec<PreparedAdvisory> = entries
+            .par_iter()
+            .map(prepare_advisory)
+            .collect::<Result<_, _>>()?;
+
+        // Serial phase: just open the transaction and execute.
         let mut conn = self
             .locked_conn
             .lock()
             .map_err(|e| format!("obtaining connection lock: {}", e))?;
         let tx = conn.transaction()?;
-        for entry in entries {
-            // Collect ecosystems from affected packages
-            let mut ecosystems = HashSet::new();
-            if let Some(affected) = entry.affected.as_ref() {
-                for a in affected {
-                    ecosystems.insert(&a.package.ecosystem);
-                }
-            }
-            let ecosystems_str = if ecosystems.is_empty() {
-                None
-            } else {
-                Some(serde_json::to_string(&ecosystems)?)
-            };
-
-            // Extract CVE from aliases (filter for aliases starting with "CVE-")
-            let cve = entry
-                .aliases
-                .as_ref()
-                .and_then(|aliases| aliases.iter().find(|alias| alias.starts_with("CVE-")))
-                .map(|s| s.as_str());
-
-            // Extract git commit URLs from references
-            let mut commit_urls = HashSet::new();
-            // Extract pull request URLs from references
-            let mut pull_request_urls = HashSet::new();
-            if let Some(references) = entry.references.as_ref() {
-                for reference in references {
-                    if let Some(commits) = extract_git_commits(&reference.url) {
-                        commit_urls.extend(commits);
-                    }
-                    if let Some(pull_requests) = extract_pull_requests(&reference.url) {
-                        pull_request_urls.extend(pull_requests);
-                    }
-                }
-            }
-            let ref_commits = if commit_urls.is_empty() {
-                None
-            } else {
-                Some(serde_json::to_string(&commit_urls)?)
-            };
-            let ref_pull_requests = if pull_request_urls.is_empty() {
-                None
-            } else {
-                Some(serde_json::to_string(&pull_request_urls)?)
-            };
-
+        for (entry, prep) in entries.iter().zip(prepared.iter()) {
             tx.execute(
                 INSERT_ADVISORY,
                 params![
@@ -231,20 +335,15 @@ impl DB {
                     entry.modified,
                     entry.published,
                     entry.withdrawn,
-                    cve,
-                    ecosystems_str,
+                    prep.cve,
+                    prep.ecosystems,
                     entry.summary,
                     entry.details,
                     entry
                         .database_specific
                         .as_ref()
                         .map(|d| d.severity.as_ref()),
-                    entry
-                        .database_specific
-                        .as_ref()
-                        .and_then(|d| d.cwe_ids.as_ref())
-                        .map(serde_json::to_value)
-                        .transpose()?,
+                    prep.cwes,
                     entry
                         .database_specific
                         .as_ref()
@@ -254,27 +353,23 @@ impl DB {
                         .database_specific
                         .as_ref()
                         .and_then(|d| d.github_reviewed_at.as_ref()),
-                    entry
-                        .database_specific
-                        .as_ref()
-                        .and_then(|d| d.nvd_published_at.as_ref())
-                        .map(serde_json::to_string)
-                        .transpose()?,
-                    ref_commits,
-                    ref_pull_requests
+                    prep.nvd_published_at,
+                    prep.ref_commits,
+                    prep.ref_pull_requests,
+                    prep.ref_issues
                 ],
             )?;
 
             if let Some(affected) = entry.affected.as_ref() {
-                for a in affected {
+                for (a, prepared_package) in affected.iter().zip(prep.affected_packages.iter()) {
                     tx.execute(
                         INSERT_AFFECTED_PACKAGE,
                         params![
                             entry.id,
                             a.package.name,
-                            serde_json::to_value(&a.package.ecosystem)?.as_str(),
-                            serde_json::to_string(&a.ranges)?,
-                            serde_json::to_string(&a.versions)?,
+                            prepared_package.ecosystem,
+                            prepared_package.ranges,
+                            prepared_package.versions,
                         ],
                     )?;
                 }
@@ -283,4 +378,150 @@ impl DB {
         tx.commit()?;
         Ok(())
     }
+
+    /// Returns the GHSAs whose `affected_packages` ranges actually cover
+    /// `version`, rather than just the raw ranges stored by `bulk_insert`.
+    /// Evaluates OSV range semantics per [`crate::versions`].
+    pub fn find_vulnerable(
+        &self,
+        ecosystem: &str,
+        package: &str,
+        version: &str,
+    ) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let conn = self
+            .locked_conn
+            .lock()
+            .map_err(|e| format!("obtaining connection lock: {}", e))?;
+        let mut stmt = conn.prepare(
+            "SELECT ghsa, ranges, versions FROM affected_packages \
+             WHERE ecosystem = ?1 AND name = ?2",
+        )?;
+        let rows = stmt.query_map(params![ecosystem, package], |row| {
+            let ghsa: String = row.get(0)?;
+            let ranges: Option<String> = row.get(1)?;
+            let row_versions: Option<String> = row.get(2)?;
+            Ok((ghsa, ranges, row_versions))
+        })?;
+
+        // An advisory can have more than one affected_packages row for the
+        // same (ecosystem, name) -- dedupe rather than returning the ghsa
+        // once per matching row.
+        let mut matches = std::collections::BTreeSet::new();
+        for row in rows {
+            let (ghsa, ranges, row_versions) = row?;
+            if versions::is_affected(ranges.as_deref(), row_versions.as_deref(), version)? {
+                matches.insert(ghsa);
+            }
+        }
+        Ok(matches.into_iter().collect())
+    }
+
+    /// Returns `(ghsa, commit_urls, pull_request_urls)` for every advisory
+    /// that still has at least one reference not yet resolved into
+    /// `fix_commits`/`fix_pull_requests`. Used by the opt-in
+    /// [`crate::github::enrich`] pass so it can be re-run without
+    /// re-fetching references it already resolved.
+    pub fn unresolved_references(
+        &self,
+    ) -> Result<Vec<(String, Vec<String>, Vec<String>)>, Box<dyn Error + Send + Sync>> {
+        let conn = self
+            .locked_conn
+            .lock()
+            .map_err(|e| format!("obtaining connection lock: {}", e))?;
+        let mut stmt = conn.prepare(
+            "SELECT ghsa, ref_commits, ref_pull_requests FROM advisories \
+             WHERE ref_commits IS NOT NULL OR ref_pull_requests IS NOT NULL",
+        )?;
+        let rows = stmt.query_map((), |row| {
+            let ghsa: String = row.get(0)?;
+            let ref_commits: Option<String> = row.get(1)?;
+            let ref_pull_requests: Option<String> = row.get(2)?;
+            Ok((ghsa, ref_commits, ref_pull_requests))
+        })?;
+
+        // Prepared once and reused across advisories rather than re-prepared
+        // per row -- this runs once per bulk enrichment pass, not per row.
+        let mut fix_commits_stmt = conn.prepare("SELECT url FROM fix_commits WHERE ghsa = ?1")?;
+        let mut fix_pull_requests_stmt =
+            conn.prepare("SELECT url FROM fix_pull_requests WHERE ghsa = ?1")?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            let (ghsa, ref_commits, ref_pull_requests) = row?;
+            let resolved_commits: HashSet<String> = fix_commits_stmt
+                .query_map(params![ghsa], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            let resolved_pull_requests: HashSet<String> = fix_pull_requests_stmt
+                .query_map(params![ghsa], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+
+            let commit_urls: Vec<String> = ref_commits
+                .map(|s| serde_json::from_str::<HashSet<String>>(&s))
+                .transpose()?
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|url| !resolved_commits.contains(url))
+                .collect();
+            let pull_request_urls: Vec<String> = ref_pull_requests
+                .map(|s| serde_json::from_str::<HashSet<String>>(&s))
+                .transpose()?
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|url| !resolved_pull_requests.contains(url))
+                .collect();
+
+            if !commit_urls.is_empty() || !pull_request_urls.is_empty() {
+                out.push((ghsa, commit_urls, pull_request_urls));
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn insert_fix_commit(
+        &self,
+        ghsa: &str,
+        fix: &FixCommit,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let conn = self
+            .locked_conn
+            .lock()
+            .map_err(|e| format!("obtaining connection lock: {}", e))?;
+        conn.execute(
+            INSERT_FIX_COMMIT,
+            params![
+                ghsa,
+                fix.url,
+                fix.sha,
+                fix.author,
+                fix.author_date,
+                serde_json::to_string(&fix.files_changed)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_fix_pull_request(
+        &self,
+        ghsa: &str,
+        fix: &FixPullRequest,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let conn = self
+            .locked_conn
+            .lock()
+            .map_err(|e| format!("obtaining connection lock: {}", e))?;
+        conn.execute(
+            INSERT_FIX_PULL_REQUEST,
+            params![
+                ghsa,
+                fix.url,
+                fix.number,
+                if fix.merged { 1 } else { 0 },
+                fix.merge_commit_sha,
+                fix.merged_at,
+                serde_json::to_string(&fix.branches)?,
+                serde_json::to_string(&fix.releases)?,
+            ],
+        )?;
+        Ok(())
+    }
 }
@@ -0,0 +1,323 @@
+//! Opt-in enrichment pass that resolves the commit/PR references already
+//! extracted by [`crate::refs`] against the GitHub API, so advisories gain
+//! fix metadata (author, changed files, merge state, release reach) beyond
+//! the bare URLs `bulk_insert` stores. This never runs as part of ingest;
+//! callers invoke [`enrich`] explicitly once rows already exist in the DB.
+
+use std::{
+    env,
+    error::Error,
+    thread,
+    time::Duration,
+};
+
+use serde::Deserialize;
+use serde_json::Value;
+use url::Url;
+
+use crate::db::DB;
+
+const API_BASE: &str = "https://api.github.com";
+const TOKEN_ENV_VAR: &str = "GITHUB_TOKEN";
+
+/// Fix metadata resolved from a single commit reference.
+#[derive(Debug, Clone)]
+pub struct FixCommit {
+    pub url: String,
+    pub sha: String,
+    pub author: Option<String>,
+    pub author_date: Option<String>,
+    pub files_changed: Vec<String>,
+}
+
+/// Fix metadata resolved from a single pull request reference.
+#[derive(Debug, Clone)]
+pub struct FixPullRequest {
+    pub url: String,
+    pub number: i64,
+    pub merged: bool,
+    pub merge_commit_sha: Option<String>,
+    pub merged_at: Option<String>,
+    pub branches: Vec<String>,
+    pub releases: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CommitResponse {
+    sha: String,
+    commit: CommitDetail,
+    files: Option<Vec<CommitFile>>,
+}
+
+#[derive(Deserialize)]
+struct CommitDetail {
+    author: Option<CommitAuthor>,
+}
+
+#[derive(Deserialize)]
+struct CommitAuthor {
+    name: Option<String>,
+    date: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CommitFile {
+    filename: String,
+}
+
+#[derive(Deserialize)]
+struct PullRequestResponse {
+    number: i64,
+    merged: bool,
+    merge_commit_sha: Option<String>,
+    merged_at: Option<String>,
+}
+
+/// Thin client over the parts of the GitHub REST API this module needs.
+/// Honors `X-RateLimit-Remaining`/`X-RateLimit-Reset` with a sleep-and-retry
+/// backoff rather than failing the whole enrichment pass on the first 403.
+pub struct GitHubClient {
+    http: reqwest::blocking::Client,
+    token: Option<String>,
+}
+
+impl GitHubClient {
+    /// Builds a client using `GITHUB_TOKEN` from the environment, if set.
+    /// Works unauthenticated too, just at GitHub's much lower rate limit.
+    pub fn from_env() -> Self {
+        Self {
+            http: reqwest::blocking::Client::new(),
+            token: env::var(TOKEN_ENV_VAR).ok(),
+        }
+    }
+
+    fn get_json(&self, url: &str) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        loop {
+            let mut req = self
+                .http
+                .get(url)
+                .header("User-Agent", "advisories-analysis")
+                .header("Accept", "application/vnd.github+json");
+            if let Some(token) = self.token.as_ref() {
+                req = req.bearer_auth(token);
+            }
+            let resp = req.send()?;
+
+            let remaining: Option<u64> = resp
+                .headers()
+                .get("x-ratelimit-remaining")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok());
+            let reset: Option<u64> = resp
+                .headers()
+                .get("x-ratelimit-reset")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok());
+
+            if resp.status() == reqwest::StatusCode::FORBIDDEN && remaining == Some(0) {
+                if let Some(reset_at) = reset {
+                    let now = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs();
+                    let wait = reset_at.saturating_sub(now).min(900);
+                    thread::sleep(Duration::from_secs(wait + 1));
+                    continue;
+                }
+            }
+
+            let resp = resp.error_for_status()?;
+            return Ok(resp.json()?);
+        }
+    }
+
+    /// `reference_url` is the forge URL the advisory itself references
+    /// (e.g. `https://github.com/foo/bar/commit/<sha>`) -- stored on the
+    /// result so `DB::unresolved_references` can match it back against
+    /// `advisories.ref_commits` and skip it on a later `enrich` run.
+    pub fn fetch_commit(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+        reference_url: &str,
+    ) -> Result<FixCommit, Box<dyn Error + Send + Sync>> {
+        let api_url = format!("{API_BASE}/repos/{owner}/{repo}/commits/{sha}");
+        let body: CommitResponse = serde_json::from_value(self.get_json(&api_url)?)?;
+        Ok(FixCommit {
+            url: reference_url.to_string(),
+            sha: body.sha,
+            author: body.commit.author.as_ref().and_then(|a| a.name.clone()),
+            author_date: body.commit.author.and_then(|a| a.date),
+            files_changed: body
+                .files
+                .unwrap_or_default()
+                .into_iter()
+                .map(|f| f.filename)
+                .collect(),
+        })
+    }
+
+    /// `reference_url` is the forge URL the advisory itself references
+    /// (e.g. `https://github.com/foo/bar/pull/<number>`) -- stored on the
+    /// result so `DB::unresolved_references` can match it back against
+    /// `advisories.ref_pull_requests` and skip it on a later `enrich` run.
+    pub fn fetch_pull_request(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: &str,
+        reference_url: &str,
+    ) -> Result<FixPullRequest, Box<dyn Error + Send + Sync>> {
+        let api_url = format!("{API_BASE}/repos/{owner}/{repo}/pulls/{number}");
+        let body: PullRequestResponse = serde_json::from_value(self.get_json(&api_url)?)?;
+
+        let branches = if body.merged {
+            self.branches_containing(owner, repo, body.merge_commit_sha.as_deref())?
+        } else {
+            Vec::new()
+        };
+        let releases = if body.merged {
+            self.releases_containing(owner, repo, body.merge_commit_sha.as_deref())?
+        } else {
+            Vec::new()
+        };
+
+        Ok(FixPullRequest {
+            url: reference_url.to_string(),
+            number: body.number,
+            merged: body.merged,
+            merge_commit_sha: body.merge_commit_sha,
+            merged_at: body.merged_at,
+            branches,
+            releases,
+        })
+    }
+
+    /// Returns the names of branches that actually contain `sha`, checked
+    /// the same way as [`Self::releases_containing`]: `branches-where-head`
+    /// only reports branches whose tip *is* the commit, not ones it merged
+    /// into, so we list branches and compare each against `sha` instead.
+    /// Only the first page of branches (up to 100) is listed and checked.
+    fn branches_containing(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: Option<&str>,
+    ) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let Some(sha) = sha else {
+            return Ok(Vec::new());
+        };
+        let url = format!("{API_BASE}/repos/{owner}/{repo}/branches?per_page=100");
+        let body = self.get_json(&url)?;
+        let names = body
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|b| b.get("name")?.as_str().map(str::to_string));
+
+        let mut matches = Vec::new();
+        for name in names {
+            let compare_url = format!("{API_BASE}/repos/{owner}/{repo}/compare/{sha}...{name}");
+            let compare = self.get_json(&compare_url)?;
+            let status = compare.get("status").and_then(|s| s.as_str()).unwrap_or("");
+            if status == "identical" || status == "ahead" {
+                matches.push(name);
+            }
+        }
+        Ok(matches)
+    }
+
+    /// Returns the tags of releases that actually contain `sha`, checked via
+    /// the compare API (`sha...tag`): a `status` of `identical` or `ahead`
+    /// means the tag's history includes `sha`, `behind`/`diverged` means it
+    /// doesn't. One compare call per release, so this is only ever run for
+    /// merged PRs during the opt-in enrichment pass, not on the ingest path.
+    /// Only the first page of releases the API returns is listed and checked.
+    fn releases_containing(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: Option<&str>,
+    ) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let Some(sha) = sha else {
+            return Ok(Vec::new());
+        };
+        let url = format!("{API_BASE}/repos/{owner}/{repo}/releases");
+        let body = self.get_json(&url)?;
+        let tags = body
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|r| r.get("tag_name")?.as_str().map(str::to_string));
+
+        let mut matches = Vec::new();
+        for tag in tags {
+            let compare_url = format!("{API_BASE}/repos/{owner}/{repo}/compare/{sha}...{tag}");
+            let compare = self.get_json(&compare_url)?;
+            let status = compare.get("status").and_then(|s| s.as_str()).unwrap_or("");
+            if status == "identical" || status == "ahead" {
+                matches.push(tag);
+            }
+        }
+        Ok(matches)
+    }
+}
+
+/// Splits a `https://github.com/<owner>/<repo>/...` URL into `(owner, repo)`.
+/// Returns `None` for non-GitHub hosts (GitLab, Bitbucket, cgit, ...) since
+/// `refs::parse_references` now extracts those too, but only GitHub has an
+/// API this module can enrich against.
+fn owner_repo(url: &str) -> Option<(String, String)> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?;
+    if host != "github.com" && !host.ends_with(".github.com") {
+        return None;
+    }
+    let mut segments = parsed.path_segments()?;
+    let owner = segments.next()?.to_string();
+    let repo = segments.next()?.to_string();
+    Some((owner, repo))
+}
+
+fn commit_sha(url: &str) -> Option<String> {
+    url.rsplit('/').next().map(str::to_string)
+}
+
+fn pull_request_number(url: &str) -> Option<String> {
+    url.rsplit('/').next().map(str::to_string)
+}
+
+/// Runs the enrichment pass: for every advisory already in `db` with
+/// unresolved commit/PR references, fetch fix metadata from GitHub and
+/// persist it to `fix_commits`/`fix_pull_requests`. Safe to re-run; it only
+/// fills in rows for references it hasn't already resolved.
+pub fn enrich(db: &DB, client: &GitHubClient) -> Result<(), Box<dyn Error + Send + Sync>> {
+    for (ghsa, commit_urls, pull_request_urls) in db.unresolved_references()? {
+        for url in commit_urls {
+            let Some((owner, repo)) = owner_repo(&url) else {
+                continue;
+            };
+            let Some(sha) = commit_sha(&url) else {
+                continue;
+            };
+            match client.fetch_commit(&owner, &repo, &sha, &url) {
+                Ok(fix) => db.insert_fix_commit(&ghsa, &fix)?,
+                Err(e) => eprintln!("enrich: commit {url} failed: {e}"),
+            }
+        }
+        for url in pull_request_urls {
+            let Some((owner, repo)) = owner_repo(&url) else {
+                continue;
+            };
+            let Some(number) = pull_request_number(&url) else {
+                continue;
+            };
+            match client.fetch_pull_request(&owner, &repo, &number, &url) {
+                Ok(fix) => db.insert_fix_pull_request(&ghsa, &fix)?,
+                Err(e) => eprintln!("enrich: pull request {url} failed: {e}"),
+            }
+        }
+    }
+    Ok(())
+}
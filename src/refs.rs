@@ -0,0 +1,285 @@
+use std::collections::HashSet;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use url::Url;
+
+/// References extracted from an advisory's `references` list, classified by
+/// what they point to rather than just pattern-matched as opaque URLs.
+#[derive(Debug, Default, Clone)]
+pub struct ParsedReferences {
+    pub commits: HashSet<String>,
+    pub pull_requests: HashSet<String>,
+    pub issues: HashSet<String>,
+}
+
+impl ParsedReferences {
+    fn is_empty(&self) -> bool {
+        self.commits.is_empty() && self.pull_requests.is_empty() && self.issues.is_empty()
+    }
+}
+
+/// A single URL's classification, independent of forge.
+enum Reference {
+    Commit(String),
+    PullRequest(String),
+    /// `/pull/123/commits/<sha>`-style links name both a PR and a commit.
+    PullRequestWithCommit(String, String),
+    Issue(String),
+}
+
+// Path-only regexes (applied after `url::Url` has already stripped the
+// query string and fragment, so we never have to special-case `?`/`#`).
+// The GitHub, GitLab and Gitea commit patterns strip an optional trailing
+// `.patch`/`.diff`; the Bitbucket and Google Source ones do not.
+
+static GITHUB_COMMIT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^/[^/]+/[^/]+/commits?/(?P<sha>[0-9a-fA-F]{7,40})(?:\.(?:patch|diff))?$").unwrap()
+});
+static GITHUB_PULL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^/[^/]+/[^/]+/pulls?/(?P<number>\d+)(?:/commits/(?P<sha>[0-9a-fA-F]{7,40}))?")
+        .unwrap()
+});
+static GITHUB_ISSUE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^/[^/]+/[^/]+/issues/(?P<number>\d+)").unwrap());
+
+static GITLAB_COMMIT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"/-/commit/(?P<sha>[0-9a-fA-F]{7,40})(?:\.(?:patch|diff))?$").unwrap());
+static GITLAB_MERGE_REQUEST: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"/-/merge_requests/(?P<number>\d+)").unwrap());
+static GITLAB_ISSUE: Lazy<Regex> = Lazy::new(|| Regex::new(r"/-/issues/(?P<number>\d+)").unwrap());
+
+static BITBUCKET_COMMIT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"/commits?/(?P<sha>[0-9a-fA-F]{7,40})$").unwrap());
+static BITBUCKET_PULL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"/pull-requests/(?P<number>\d+)").unwrap());
+static BITBUCKET_ISSUE: Lazy<Regex> = Lazy::new(|| Regex::new(r"/issues/(?P<number>\d+)").unwrap());
+
+static GITEA_COMMIT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^/[^/]+/[^/]+/commit/(?P<sha>[0-9a-fA-F]{7,40})(?:\.(?:patch|diff))?$").unwrap()
+});
+static GITEA_PULL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^/[^/]+/[^/]+/pulls/(?P<number>\d+)").unwrap());
+static GITEA_ISSUE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^/[^/]+/[^/]+/issues/(?P<number>\d+)").unwrap());
+
+// cgit / gitweb: `.../commit/?id=<sha>` (query is matched separately since
+// these forges put the hash in a query param rather than the path).
+static CGIT_GITWEB_COMMIT_PATH: Lazy<Regex> = Lazy::new(|| Regex::new(r"/commit/?$").unwrap());
+static CGIT_GITWEB_COMMIT_QUERY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:^|&)(?:id|h)=(?P<sha>[0-9a-fA-F]{7,40})(?:&|$)").unwrap());
+
+// Google Source (googlesource.com): `/+/<sha>` or `/+/<sha>^!`.
+static GOOGLESOURCE_COMMIT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"/\+/(?P<sha>[0-9a-fA-F]{7,40})\^?!?$").unwrap());
+
+/// Builds the canonical `https://<host><path up to and including the sha>`
+/// URL, dropping any `.patch`/`.diff` suffix the regex matched after the
+/// hash -- it lives outside the `sha` capture, so slicing `path` at its end
+/// discards it. The query string and `#anchor` are never part of `path`.
+fn canonical_commit_url(host: &str, path: &str, sha: regex::Match) -> String {
+    format!("https://{}{}", host, &path[..sha.end()])
+}
+
+fn classify(parsed: &Url) -> Option<Reference> {
+    let host = parsed.host_str()?;
+    let path = parsed.path();
+
+    if host == "github.com" || host.ends_with(".github.com") {
+        if let Some(caps) = GITHUB_PULL.captures(path) {
+            let number_match = caps.name("number").unwrap();
+            let pr_url = format!("https://{}{}", host, &path[..number_match.end()]);
+            return match caps.name("sha") {
+                Some(sha) => {
+                    let owner_repo = &path[1..number_match.start()]
+                        .splitn(3, '/')
+                        .take(2)
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    Some(Reference::PullRequestWithCommit(
+                        pr_url,
+                        format!("https://{}/{}/commit/{}", host, owner_repo, sha.as_str()),
+                    ))
+                }
+                None => Some(Reference::PullRequest(pr_url)),
+            };
+        }
+        if let Some(caps) = GITHUB_COMMIT.captures(path) {
+            return Some(Reference::Commit(canonical_commit_url(
+                host,
+                path,
+                caps.name("sha").unwrap(),
+            )));
+        }
+        if GITHUB_ISSUE.is_match(path) {
+            return Some(Reference::Issue(parsed.as_str().to_string()));
+        }
+    } else if host == "gitlab.com" || host.starts_with("gitlab.") {
+        if GITLAB_MERGE_REQUEST.is_match(path) {
+            return Some(Reference::PullRequest(parsed.as_str().to_string()));
+        }
+        if let Some(caps) = GITLAB_COMMIT.captures(path) {
+            return Some(Reference::Commit(canonical_commit_url(
+                host,
+                path,
+                caps.name("sha").unwrap(),
+            )));
+        }
+        if GITLAB_ISSUE.is_match(path) {
+            return Some(Reference::Issue(parsed.as_str().to_string()));
+        }
+    } else if host == "bitbucket.org" {
+        if BITBUCKET_PULL.is_match(path) {
+            return Some(Reference::PullRequest(parsed.as_str().to_string()));
+        }
+        if let Some(caps) = BITBUCKET_COMMIT.captures(path) {
+            return Some(Reference::Commit(canonical_commit_url(
+                host,
+                path,
+                caps.name("sha").unwrap(),
+            )));
+        }
+        if BITBUCKET_ISSUE.is_match(path) {
+            return Some(Reference::Issue(parsed.as_str().to_string()));
+        }
+    } else if host.starts_with("gitea.") || host == "codeberg.org" {
+        if GITEA_PULL.is_match(path) {
+            return Some(Reference::PullRequest(parsed.as_str().to_string()));
+        }
+        if let Some(caps) = GITEA_COMMIT.captures(path) {
+            return Some(Reference::Commit(canonical_commit_url(
+                host,
+                path,
+                caps.name("sha").unwrap(),
+            )));
+        }
+        if GITEA_ISSUE.is_match(path) {
+            return Some(Reference::Issue(parsed.as_str().to_string()));
+        }
+    } else if host.ends_with(".googlesource.com") {
+        if let Some(caps) = GOOGLESOURCE_COMMIT.captures(path) {
+            return Some(Reference::Commit(canonical_commit_url(
+                host,
+                path,
+                caps.name("sha").unwrap(),
+            )));
+        }
+    } else if CGIT_GITWEB_COMMIT_PATH.is_match(path) {
+        // cgit/gitweb put the hash in a query param (`?id=` or `?h=`)
+        // rather than the path, so match the query separately.
+        if let Some(caps) = CGIT_GITWEB_COMMIT_QUERY.captures(parsed.query().unwrap_or(""))
+        {
+            let _ = &caps["sha"];
+            return Some(Reference::Commit(parsed.as_str().to_string()));
+        }
+    }
+
+    None
+}
+
+/// Parse and classify each reference URL. URLs that fail to parse or don't
+/// match any known forge layout (e.g. advisory mirrors, vendor bulletins)
+/// are skipped and don't populate `commits`/`pull_requests`/`issues`.
+pub fn parse_references<'a, I: IntoIterator<Item = &'a str>>(urls: I) -> ParsedReferences {
+    let mut out = ParsedReferences::default();
+    for url in urls {
+        let Ok(parsed) = Url::parse(url) else {
+            continue;
+        };
+        match classify(&parsed) {
+            Some(Reference::Commit(url)) => {
+                out.commits.insert(url);
+            }
+            Some(Reference::PullRequest(url)) => {
+                out.pull_requests.insert(url);
+            }
+            Some(Reference::PullRequestWithCommit(pr_url, commit_url)) => {
+                out.pull_requests.insert(pr_url);
+                out.commits.insert(commit_url);
+            }
+            Some(Reference::Issue(url)) => {
+                out.issues.insert(url);
+            }
+            None => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(urls: &[&str]) -> ParsedReferences {
+        parse_references(urls.iter().copied())
+    }
+
+    #[test]
+    fn github_commit_with_patch_suffix() {
+        let refs = parsed(&["https://github.com/foo/bar/commit/deadbeefcafe1234567890abcdef1234567890.patch"]);
+        assert_eq!(refs.commits.len(), 1);
+        assert!(refs.pull_requests.is_empty());
+        assert_eq!(
+            refs.commits.iter().next().unwrap(),
+            "https://github.com/foo/bar/commit/deadbeefcafe1234567890abcdef1234567890"
+        );
+    }
+
+    #[test]
+    fn gitlab_commit_with_diff_suffix_is_canonicalized() {
+        let refs = parsed(&[
+            "https://gitlab.com/foo/bar/-/commit/deadbeefcafe1234567890abcdef1234567890.diff?query=1#anchor",
+        ]);
+        assert_eq!(
+            refs.commits.iter().next().unwrap(),
+            "https://gitlab.com/foo/bar/-/commit/deadbeefcafe1234567890abcdef1234567890"
+        );
+    }
+
+    #[test]
+    fn github_pull_records_pr_and_linked_commit() {
+        let refs = parsed(&[
+            "https://github.com/foo/bar/pull/42/commits/deadbeefcafe1234567890abcdef1234567890",
+        ]);
+        assert_eq!(refs.pull_requests.len(), 1);
+        assert_eq!(refs.commits.len(), 1);
+    }
+
+    #[test]
+    fn gitlab_merge_request_and_commit() {
+        let refs = parsed(&[
+            "https://gitlab.com/foo/bar/-/merge_requests/7",
+            "https://gitlab.com/foo/bar/-/commit/deadbeefcafe1234567890abcdef1234567890",
+        ]);
+        assert_eq!(refs.pull_requests.len(), 1);
+        assert_eq!(refs.commits.len(), 1);
+    }
+
+    #[test]
+    fn cgit_commit_query_param() {
+        let refs = parsed(&[
+            "https://git.kernel.org/pub/scm/linux/kernel/git/torvalds/linux.git/commit/?id=deadbeefcafe1234567890abcdef1234567890",
+        ]);
+        assert_eq!(refs.commits.len(), 1);
+    }
+
+    #[test]
+    fn googlesource_commit() {
+        let refs = parsed(&[
+            "https://chromium.googlesource.com/chromium/src/+/deadbeefcafe1234567890abcdef1234567890",
+        ]);
+        assert_eq!(refs.commits.len(), 1);
+    }
+
+    #[test]
+    fn unrecognized_host_is_ignored() {
+        let refs = parsed(&["https://nvd.nist.gov/vuln/detail/CVE-2021-1234"]);
+        assert!(refs.is_empty());
+    }
+
+    #[test]
+    fn spoofed_codeberg_host_is_not_classified_as_codeberg() {
+        let refs = parsed(&["https://codeberg.org.attacker.test/foo/bar/pulls/42"]);
+        assert!(refs.is_empty());
+    }
+}
@@ -0,0 +1,111 @@
+//! Read-only HTTP service exposing advisory lookups, modeled on the "serve a
+//! local drop over HTTP" pattern: a small embedded server with a health
+//! endpoint that only ever reads, so it can run alongside an ingest process
+//! without contending for write access to the database.
+//!
+//! Routes:
+//! - `GET /healthz`
+//! - `GET /advisories/<ghsa>`
+//! - `GET /advisories?cve=<cve>`
+//! - `GET /packages/<ecosystem>/<name>`
+//! - `GET /vulnerable?ecosystem=<e>&package=<p>&version=<v>`
+
+use std::error::Error;
+
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::db::DB;
+
+fn json_header() -> Header {
+    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap()
+}
+
+fn respond_json(request: tiny_http::Request, status: u16, body: &str) {
+    let response = Response::from_string(body)
+        .with_status_code(status)
+        .with_header(json_header());
+    let _ = request.respond(response);
+}
+
+fn query_param<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    query.split('&').find_map(|pair| {
+        let (k, v) = pair.split_once('=')?;
+        (k == key).then_some(v)
+    })
+}
+
+/// Splits a request URL into `(path_segments, query_string)`.
+fn split_url(url: &str) -> (Vec<&str>, &str) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    (path.split('/').filter(|s| !s.is_empty()).collect(), query)
+}
+
+fn handle(db: &DB, request: tiny_http::Request) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if *request.method() != Method::Get {
+        respond_json(request, 405, r#"{"error":"method not allowed"}"#);
+        return Ok(());
+    }
+
+    let (segments, query) = split_url(request.url());
+    let segments: Vec<&str> = segments;
+
+    match segments.as_slice() {
+        ["healthz"] => respond_json(request, 200, r#"{"status":"ok"}"#),
+
+        ["advisories", ghsa] => match db.get_by_ghsa(ghsa)? {
+            Some(advisory) => respond_json(request, 200, &serde_json::to_string(&advisory)?),
+            None => respond_json(request, 404, r#"{"error":"not found"}"#),
+        },
+
+        ["advisories"] => match query_param(query, "cve") {
+            Some(cve) => {
+                let advisories = db.get_by_cve(cve)?;
+                respond_json(request, 200, &serde_json::to_string(&advisories)?)
+            }
+            None => respond_json(request, 400, r#"{"error":"missing cve parameter"}"#),
+        },
+
+        ["packages", ecosystem, name] => {
+            let advisories = db.get_by_package(ecosystem, name)?;
+            respond_json(request, 200, &serde_json::to_string(&advisories)?)
+        }
+
+        ["vulnerable"] => {
+            let ecosystem = query_param(query, "ecosystem");
+            let package = query_param(query, "package");
+            let version = query_param(query, "version");
+            match (ecosystem, package, version) {
+                (Some(ecosystem), Some(package), Some(version)) => {
+                    let ghsas = db.find_vulnerable(ecosystem, package, version)?;
+                    respond_json(request, 200, &serde_json::to_string(&ghsas)?)
+                }
+                _ => respond_json(
+                    request,
+                    400,
+                    r#"{"error":"missing ecosystem, package, or version parameter"}"#,
+                ),
+            }
+        }
+
+        _ => respond_json(request, 404, r#"{"error":"not found"}"#),
+    }
+
+    Ok(())
+}
+
+/// Opens `db_path` read-only and serves advisory lookups over HTTP on
+/// `addr` (e.g. `"127.0.0.1:8080"`) until the process is killed. Blocking,
+/// single-threaded: queries are cheap point lookups, and not sharing a
+/// writer means there's never a reason to scale this past one thread.
+pub fn serve(db_path: &str, addr: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let db = DB::open_read_only(db_path)?;
+    let server = Server::http(addr).map_err(|e| format!("binding {addr}: {e}"))?;
+
+    for request in server.incoming_requests() {
+        if let Err(e) = handle(&db, request) {
+            eprintln!("serve: request failed: {e}");
+        }
+    }
+
+    Ok(())
+}
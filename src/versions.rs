@@ -0,0 +1,196 @@
+//! Evaluates OSV `affected[].ranges`/`affected[].versions` against a concrete
+//! version, so [`crate::db::DB::find_vulnerable`] can answer "is this
+//! version affected?" instead of just handing back the raw stored ranges.
+
+use std::{cmp::Ordering, error::Error};
+
+use semver::Version;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum RangeType {
+    Semver,
+    Ecosystem,
+    Git,
+}
+
+#[derive(Debug, Deserialize)]
+struct Range {
+    #[serde(rename = "type")]
+    range_type: RangeType,
+    events: Vec<Event>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Event {
+    introduced: Option<String>,
+    fixed: Option<String>,
+    last_affected: Option<String>,
+    #[serde(default)]
+    limit: Option<String>,
+}
+
+/// Compares two version strings under the given range's semantics.
+/// `SEMVER` ranges use proper semver ordering; `ECOSYSTEM`/`GIT` ranges fall
+/// back to a dotted-numeric comparison, since there's no single correct
+/// scheme across every ecosystem's version syntax.
+fn compare(range_type: &RangeType, a: &str, b: &str) -> Ordering {
+    match range_type {
+        RangeType::Semver => match (Version::parse(a), Version::parse(b)) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => compare_ecosystem(a, b),
+        },
+        RangeType::Ecosystem | RangeType::Git => compare_ecosystem(a, b),
+    }
+}
+
+/// Best-effort ecosystem version compare: split on `.`, compare numeric
+/// segments numerically and non-numeric segments lexically; when all shared
+/// segments are equal, the side with more segments is the greater one.
+fn compare_ecosystem(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+    loop {
+        match (a_parts.next(), b_parts.next()) {
+            (Some(a_part), Some(b_part)) => {
+                let ordering = match (a_part.parse::<u64>(), b_part.parse::<u64>()) {
+                    (Ok(a_num), Ok(b_num)) => a_num.cmp(&b_num),
+                    _ => a_part.cmp(b_part),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (None, None) => return Ordering::Equal,
+        }
+    }
+}
+
+fn covers(range: &Range, version: &str) -> bool {
+    let mut events: Vec<&Event> = range.events.iter().collect();
+    events.sort_by(|a, b| {
+        let a_value = a
+            .introduced
+            .as_deref()
+            .or(a.fixed.as_deref())
+            .or(a.last_affected.as_deref())
+            .or(a.limit.as_deref())
+            .unwrap_or("0");
+        let b_value = b
+            .introduced
+            .as_deref()
+            .or(b.fixed.as_deref())
+            .or(b.last_affected.as_deref())
+            .or(b.limit.as_deref())
+            .unwrap_or("0");
+        match (a_value, b_value) {
+            ("0", "0") => Ordering::Equal,
+            ("0", _) => Ordering::Less,
+            (_, "0") => Ordering::Greater,
+            _ => compare(&range.range_type, a_value, b_value),
+        }
+    });
+
+    let mut affected = false;
+    for event in events {
+        if let Some(introduced) = event.introduced.as_deref() {
+            if introduced == "0" || compare(&range.range_type, introduced, version) != Ordering::Greater {
+                affected = true;
+            }
+        } else if let Some(fixed) = event.fixed.as_deref() {
+            if compare(&range.range_type, fixed, version) != Ordering::Greater {
+                affected = false;
+            }
+        } else if let Some(last_affected) = event.last_affected.as_deref() {
+            // `last_affected` only ever turns affected *off*, once `version`
+            // passes it -- it must not flip a version that hasn't even
+            // reached the preceding `introduced` boundary yet to affected.
+            if compare(&range.range_type, last_affected, version) == Ordering::Less {
+                affected = false;
+            }
+        }
+    }
+    affected
+}
+
+/// Returns whether `version` is covered by the given serialized
+/// `affected_packages.ranges`/`affected_packages.versions` columns, as
+/// stored by [`crate::db::DB::bulk_insert`].
+pub fn is_affected(
+    ranges_json: Option<&str>,
+    versions_json: Option<&str>,
+    version: &str,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    // `bulk_insert` serializes an absent `ranges`/`versions` field as the
+    // literal string `"null"` (via `serde_json::to_string` on `None`),
+    // rather than binding SQL NULL, so treat that -- and any other non-array
+    // payload -- as "no data" instead of failing the whole lookup.
+    if let Some(versions_json) = versions_json {
+        let versions: Vec<String> =
+            serde_json::from_str(versions_json).unwrap_or_default();
+        if versions.iter().any(|v| v == version) {
+            return Ok(true);
+        }
+    }
+
+    if let Some(ranges_json) = ranges_json {
+        let ranges: Vec<Range> = serde_json::from_str(ranges_json).unwrap_or_default();
+        if ranges.iter().any(|range| covers(range, version)) {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn semver_introduced_and_fixed() {
+        let ranges = r#"[{"type":"SEMVER","events":[{"introduced":"0"},{"fixed":"1.2.3"}]}]"#;
+        assert!(is_affected(Some(ranges), None, "1.0.0").unwrap());
+        assert!(!is_affected(Some(ranges), None, "1.2.3").unwrap());
+        assert!(!is_affected(Some(ranges), None, "1.5.0").unwrap());
+    }
+
+    #[test]
+    fn semver_last_affected_is_inclusive() {
+        let ranges = r#"[{"type":"SEMVER","events":[{"introduced":"0"},{"last_affected":"1.2.3"}]}]"#;
+        assert!(is_affected(Some(ranges), None, "1.2.3").unwrap());
+        assert!(!is_affected(Some(ranges), None, "1.2.4").unwrap());
+    }
+
+    #[test]
+    fn last_affected_does_not_cover_versions_below_introduced() {
+        let ranges = r#"[{"type":"SEMVER","events":[{"introduced":"1.0.0"},{"last_affected":"2.0.0"}]}]"#;
+        assert!(!is_affected(Some(ranges), None, "0.5.0").unwrap());
+        assert!(is_affected(Some(ranges), None, "1.5.0").unwrap());
+        assert!(is_affected(Some(ranges), None, "2.0.0").unwrap());
+        assert!(!is_affected(Some(ranges), None, "2.0.1").unwrap());
+    }
+
+    #[test]
+    fn null_versions_column_is_treated_as_absent() {
+        let ranges = r#"[{"type":"SEMVER","events":[{"introduced":"0"},{"fixed":"1.2.3"}]}]"#;
+        assert!(is_affected(Some(ranges), Some("null"), "1.0.0").unwrap());
+        assert!(!is_affected(Some("null"), Some("null"), "1.0.0").unwrap());
+    }
+
+    #[test]
+    fn explicit_versions_list_matches_exactly() {
+        assert!(is_affected(None, Some(r#"["1.0.0","2.0.0"]"#), "2.0.0").unwrap());
+        assert!(!is_affected(None, Some(r#"["1.0.0","2.0.0"]"#), "1.5.0").unwrap());
+    }
+
+    #[test]
+    fn ecosystem_range_falls_back_to_dotted_compare() {
+        let ranges = r#"[{"type":"ECOSYSTEM","events":[{"introduced":"0"},{"fixed":"2.1"}]}]"#;
+        assert!(is_affected(Some(ranges), None, "2.0").unwrap());
+        assert!(!is_affected(Some(ranges), None, "2.1").unwrap());
+    }
+}